@@ -1,12 +1,42 @@
 use alsa_sys as ffi;
+use dasp::sample::conv;
 use nix::errno::Errno;
+use nix::libc;
+use nix::unistd;
 use std::collections::VecDeque;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
+use std::os::unix::io::RawFd;
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Sample formats probed when querying a device's capabilities. This mirrors the set of formats
+/// ALSA is realistically asked to provide on Linux audio hardware; exotic formats (e.g. packed
+/// 24-bit) aren't worth probing since nothing in this crate can use them yet.
+const CANDIDATE_FORMATS: &[ffi::snd_pcm_format_t] = &[
+    ffi::SND_PCM_FORMAT_U8,
+    ffi::SND_PCM_FORMAT_S16_LE,
+    ffi::SND_PCM_FORMAT_S16_BE,
+    ffi::SND_PCM_FORMAT_S32_LE,
+    ffi::SND_PCM_FORMAT_S32_BE,
+    ffi::SND_PCM_FORMAT_FLOAT_LE,
+    ffi::SND_PCM_FORMAT_FLOAT_BE,
+];
 
 #[derive(Debug)]
 pub enum Error {
     InitError(Errno),
+    /// The device ran out of buffered audio and ALSA dropped the stream (`EPIPE`). Recovered
+    /// automatically by re-preparing the stream.
+    Underrun,
+    /// The device was suspended, e.g. by a power-management event (`ESTRPIPE`). Recovered
+    /// automatically once `snd_pcm_resume` reports the device is ready again.
+    Suspended,
+    /// A [`StreamHandle`] method was called after the run loop already exited (via `stop`, or
+    /// on a `poll` error). A loop that ends by panicking is not detected.
+    StreamStopped,
 }
 
 impl From<i32> for Error {
@@ -16,45 +46,574 @@ impl From<i32> for Error {
     }
 }
 
+/// Try to recover from a negative `snd_pcm_writei`/`snd_pcm_readi` return, handling the two
+/// cases ALSA expects callers to handle themselves: an underrun (`EPIPE`), recovered by
+/// `snd_pcm_prepare`, and suspension (`ESTRPIPE`), recovered by polling `snd_pcm_resume` until
+/// the device wakes up and then `snd_pcm_prepare`. `on_underrun`, if set, is notified of which
+/// case occurred. Returns `Ok(())` once the stream is ready to retry, or the original error if
+/// it wasn't one of these two recoverable cases.
+fn recover(
+    handle: *mut ffi::snd_pcm_t,
+    ret: i64,
+    on_underrun: &Option<Arc<dyn Fn(Error) + Send + Sync>>,
+) -> Result<(), Error> {
+    let errno = Errno::from_i32(-ret as i32);
+
+    match errno {
+        Errno::EPIPE => {
+            if let Some(callback) = on_underrun {
+                callback(Error::Underrun);
+            }
+
+            unsafe { code!(ffi::snd_pcm_prepare(handle)).map_err(Error::from) }
+        }
+        Errno::ESTRPIPE => {
+            if let Some(callback) = on_underrun {
+                callback(Error::Suspended);
+            }
+
+            unsafe {
+                loop {
+                    let ret = ffi::snd_pcm_resume(handle);
+                    if Errno::from_i32(-ret as i32) != Errno::EAGAIN {
+                        break;
+                    }
+
+                    thread::sleep(Duration::from_millis(100));
+                }
+
+                code!(ffi::snd_pcm_prepare(handle)).map_err(Error::from)
+            }
+        }
+        _ => Err(Error::from(ret as i32)),
+    }
+}
+
+/// A self-pipe used to wake a blocked `poll` from another thread. Mirrors the trigger cpal's
+/// ALSA backend uses to interrupt `snd_pcm_wait`: writing a byte to `write_fd` makes `read_fd`
+/// ready, which wakes any `poll` waiting on it.
+struct Trigger {
+    read_fd: RawFd,
+    write_fd: RawFd,
+}
+
+impl Trigger {
+    fn new() -> Result<Self, Error> {
+        let (read_fd, write_fd) = unistd::pipe().map_err(Error::InitError)?;
+
+        Ok(Self { read_fd, write_fd })
+    }
+
+    fn fire(&self) {
+        let _ = unistd::write(self.write_fd, &[1u8]);
+    }
+
+    /// Drain every byte written to the pipe so a later `poll` only wakes on fresh activity.
+    fn drain(&self) {
+        let mut scratch = [0u8; 64];
+        while let Ok(n) = unistd::read(self.read_fd, &mut scratch) {
+            if n < scratch.len() {
+                break;
+            }
+        }
+    }
+}
+
+impl Drop for Trigger {
+    fn drop(&mut self) {
+        let _ = unistd::close(self.read_fd);
+        let _ = unistd::close(self.write_fd);
+    }
+}
+
+/// Apply a pause/resume transition from the run loop's own thread, in response to
+/// [`StreamHandle::pause`]/[`StreamHandle::resume`] flipping the `paused` flag. Falls back to
+/// `snd_pcm_drop` on pause and `snd_pcm_drop` + `snd_pcm_prepare` on resume if the device
+/// doesn't support hardware pause/resume.
+unsafe fn apply_pause_state(handle: *mut ffi::snd_pcm_t, paused: bool) -> Result<(), Error> {
+    if paused {
+        if ffi::snd_pcm_pause(handle, 1) < 0 {
+            code!(ffi::snd_pcm_drop(handle))?;
+        }
+    } else if ffi::snd_pcm_pause(handle, 0) < 0 {
+        code!(ffi::snd_pcm_drop(handle))?;
+        code!(ffi::snd_pcm_prepare(handle))?;
+    }
+
+    Ok(())
+}
+
+/// Block until either the device has fds ready or the trigger fires. Returns `true` if the
+/// trigger is what woke the call.
+fn wait_for_activity(handle: *mut ffi::snd_pcm_t, trigger_fd: RawFd) -> Result<bool, Error> {
+    unsafe {
+        let count = ffi::snd_pcm_poll_descriptors_count(handle);
+        if count < 0 {
+            return Err(Error::from(count));
+        }
+
+        let mut fds = vec![libc::pollfd { fd: 0, events: 0, revents: 0 }; count as usize + 1];
+        let written = ffi::snd_pcm_poll_descriptors(handle, fds.as_mut_ptr() as *mut _, count as u32);
+
+        if written < 0 {
+            return Err(Error::from(written));
+        }
+
+        let trigger_idx = written as usize;
+        fds.truncate(trigger_idx + 1);
+        fds[trigger_idx] = libc::pollfd { fd: trigger_fd, events: libc::POLLIN, revents: 0 };
+
+        loop {
+            let ret = libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1);
+            if ret >= 0 {
+                break;
+            }
+
+            // `poll` returns -1 with errno set, not an ALSA-style negative return code, so the
+            // error has to come from errno directly rather than `Error::from(ret)`.
+            let errno = Errno::last();
+            if errno != Errno::EINTR {
+                return Err(Error::InitError(errno));
+            }
+        }
+
+        Ok(fds[trigger_idx].revents & libc::POLLIN != 0)
+    }
+}
+
+/// Block until the trigger fires, without also watching the PCM fds. Used while paused: a
+/// paused PCM's fds aren't guaranteed to stop reporting ready (`avail` can stay past
+/// `avail_min`), so polling them here would busy-spin instead of actually blocking.
+fn wait_for_trigger(trigger_fd: RawFd) -> Result<(), Error> {
+    let mut pollfd = libc::pollfd { fd: trigger_fd, events: libc::POLLIN, revents: 0 };
+
+    loop {
+        let ret = unsafe { libc::poll(&mut pollfd, 1, -1) };
+        if ret >= 0 {
+            break;
+        }
+
+        // Same as in `wait_for_activity`: this is a raw libc return, so the error lives in
+        // errno, not in the negative-return-code convention ALSA's own calls use.
+        let errno = Errno::last();
+        if errno != Errno::EINTR {
+            return Err(Error::InitError(errno));
+        }
+    }
+
+    Ok(())
+}
+
+/// A handle to a running [`Device::run`] loop, used to pause, resume, or stop it from another
+/// thread. Deliberately holds no reference to the PCM handle itself: ALSA's PCM API isn't safe
+/// to call concurrently from two threads, so every `snd_pcm_*` call is made from the run loop's
+/// own thread. `StreamHandle` only flips atomics and wakes the loop via `trigger`.
+pub struct StreamHandle {
+    trigger: Arc<Trigger>,
+    running: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+}
+
+impl StreamHandle {
+    /// Pause playback in place. On a device with hardware pause support it keeps its buffered
+    /// audio and `resume` picks up where it left off; otherwise the buffered audio is dropped.
+    /// Only flips a flag and wakes the run loop: the actual `snd_pcm_pause` call
+    /// happens on the loop's own thread, since it already owns the PCM handle.
+    pub fn pause(&self) -> Result<(), Error> {
+        if !self.running.load(Ordering::SeqCst) {
+            return Err(Error::StreamStopped);
+        }
+
+        self.paused.store(true, Ordering::SeqCst);
+        self.trigger.fire();
+
+        Ok(())
+    }
+
+    /// Resume playback after a `pause`. Only flips a flag and wakes the run loop: the actual
+    /// `snd_pcm_pause`/`snd_pcm_drop`/`snd_pcm_prepare` calls happen on the loop's own thread,
+    /// since it already owns the PCM handle.
+    pub fn resume(&self) -> Result<(), Error> {
+        if !self.running.load(Ordering::SeqCst) {
+            return Err(Error::StreamStopped);
+        }
+
+        self.paused.store(false, Ordering::SeqCst);
+        self.trigger.fire();
+
+        Ok(())
+    }
+
+    /// Signal the run loop to exit. Returns immediately; the loop itself breaks as soon as it
+    /// wakes from `poll`. A no-op if the loop has already stopped.
+    pub fn stop(&self) {
+        if self.running.swap(false, Ordering::SeqCst) {
+            self.trigger.fire();
+        }
+    }
+}
+
+/// Enumerate the PCM devices ALSA knows about, as reported by `snd_device_name_hint`.
+///
+/// The returned list includes ALSA's virtual devices (`default`, `pulse`, ...) alongside the
+/// hardware cards, in whatever order ALSA's hint iteration produces them.
+pub fn devices() -> Result<Vec<DeviceInfo>, Error> {
+    unsafe {
+        let pcm = CStr::from_bytes_with_nul_unchecked(b"pcm\0").as_ptr();
+        let mut hints: *mut *mut nix::libc::c_void = ptr::null_mut();
+
+        code!(ffi::snd_device_name_hint(-1, pcm, &mut hints))?;
+
+        let mut devices = Vec::new();
+        let mut cursor = hints;
+
+        while !(*cursor).is_null() {
+            let name_field = CStr::from_bytes_with_nul_unchecked(b"NAME\0").as_ptr();
+            let desc_field = CStr::from_bytes_with_nul_unchecked(b"DESC\0").as_ptr();
+
+            let name = ffi::snd_device_name_get_hint(*cursor, name_field);
+            let description = ffi::snd_device_name_get_hint(*cursor, desc_field);
+
+            if !name.is_null() {
+                let device_name = CStr::from_ptr(name).to_string_lossy().into_owned();
+                let device_description = if description.is_null() {
+                    String::new()
+                } else {
+                    CStr::from_ptr(description).to_string_lossy().into_owned()
+                };
+
+                devices.push(DeviceInfo { name: device_name, description: device_description });
+            }
+
+            if !name.is_null() {
+                nix::libc::free(name as *mut nix::libc::c_void);
+            }
+            if !description.is_null() {
+                nix::libc::free(description as *mut nix::libc::c_void);
+            }
+
+            cursor = cursor.add(1);
+        }
+
+        ffi::snd_device_name_free_hint(hints);
+
+        Ok(devices)
+    }
+}
+
+/// A PCM device as reported by ALSA's device name hints, identified by the name that can be
+/// passed back to [`Device::with_config`].
+#[derive(Debug, Clone)]
+pub struct DeviceInfo {
+    name: String,
+    description: String,
+}
+
+impl DeviceInfo {
+    /// The name ALSA expects when opening this device, e.g. `"hw:0,0"` or `"default"`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// A human-readable description of the device, as reported by ALSA.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Probe the set of sample formats, channel counts, and sample rates this device supports.
+    ///
+    /// This opens the device non-blocking just long enough to query its `snd_pcm_hw_params_t`,
+    /// then closes it again; it does not hold the device open.
+    pub fn supported_formats(&self) -> Result<Vec<SupportedFormat>, Error> {
+        let device_name = CString::new(self.name.as_str()).expect("device name contains a nul byte");
+
+        let handle = unsafe {
+            ptr_init!(*mut ffi::snd_pcm_t, |p| ffi::snd_pcm_open(
+                    p,
+                    device_name.as_ptr(),
+                    ffi::SND_PCM_STREAM_PLAYBACK,
+                    ffi::SND_PCM_NONBLOCK
+            ))?
+        };
+
+        // Closes the handle on drop so a `?` partway through probing doesn't leak it.
+        let handle = PcmGuard(handle);
+
+        let mut hw_params = HwParams::new()?;
+
+        unsafe {
+            code!(ffi::snd_pcm_hw_params_any(handle.0, hw_params.as_mut_ptr()))?;
+        }
+
+        let mut formats = Vec::new();
+
+        for &format in CANDIDATE_FORMATS {
+            unsafe {
+                if ffi::snd_pcm_hw_params_test_format(handle.0, hw_params.as_mut_ptr(), format) < 0 {
+                    continue;
+                }
+
+                let mut min_rate = 0;
+                let mut max_rate = 0;
+                let mut min_channels = 0;
+                let mut max_channels = 0;
+
+                code!(ffi::snd_pcm_hw_params_get_rate_min(hw_params.as_mut_ptr(), &mut min_rate, &mut 0))?;
+                code!(ffi::snd_pcm_hw_params_get_rate_max(hw_params.as_mut_ptr(), &mut max_rate, &mut 0))?;
+                code!(ffi::snd_pcm_hw_params_get_channels_min(hw_params.as_mut_ptr(), &mut min_channels))?;
+                code!(ffi::snd_pcm_hw_params_get_channels_max(hw_params.as_mut_ptr(), &mut max_channels))?;
+
+                formats.push(SupportedFormat {
+                    format,
+                    min_rate,
+                    max_rate,
+                    min_channels,
+                    max_channels,
+                });
+            }
+        }
+
+        Ok(formats)
+    }
+}
+
+/// Closes a raw PCM handle on drop, so an early return (e.g. via `?`) can't leak it.
+struct PcmGuard(*mut ffi::snd_pcm_t);
+
+impl Drop for PcmGuard {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::snd_pcm_close(self.0);
+        }
+    }
+}
+
+/// One sample format a device supports, along with the device's channel count and sample rate
+/// ranges. The ranges are queried over the device's whole configuration space; they are not
+/// narrowed to this format.
+#[derive(Debug, Clone)]
+pub struct SupportedFormat {
+    format: ffi::snd_pcm_format_t,
+    min_rate: u32,
+    max_rate: u32,
+    min_channels: u32,
+    max_channels: u32,
+}
+
+impl SupportedFormat {
+    pub fn format(&self) -> ffi::snd_pcm_format_t {
+        self.format
+    }
+
+    /// This format as a [`SampleFormat`], for passing straight into a [`DeviceConfig`]. `None` if
+    /// ALSA's format has no `SampleFormat` equivalent (e.g. the signed 32-bit formats), in which
+    /// case this device can't be driven through [`Device::with_config`] at this format.
+    pub fn sample_format(&self) -> Option<SampleFormat> {
+        SampleFormat::from_alsa(self.format)
+    }
+
+    pub fn rate_range(&self) -> (u32, u32) {
+        (self.min_rate, self.max_rate)
+    }
+
+    pub fn channel_range(&self) -> (u32, u32) {
+        (self.min_channels, self.max_channels)
+    }
+}
+
+/// Which way audio flows between the application and the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Output audio to the device. Drive this with [`Device::run`].
+    Playback,
+    /// Record audio from the device. Drive this with [`Device::run_capture`].
+    Capture,
+}
+
+/// The representation of a single sample, as negotiated with the device. Mirrors cpal's
+/// `SampleFormat`: the caller picks one, and [`Device::run`] hands back a matching
+/// [`OutputBuffer`] variant rather than assuming every device speaks `f32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+    U8,
+    I16,
+    F32,
+}
+
+impl SampleFormat {
+    fn to_alsa(self) -> ffi::snd_pcm_format_t {
+        match self {
+            SampleFormat::U8 => ffi::SND_PCM_FORMAT_U8,
+            SampleFormat::I16 if cfg!(target_endian = "big") => ffi::SND_PCM_FORMAT_S16_BE,
+            SampleFormat::I16 => ffi::SND_PCM_FORMAT_S16_LE,
+            SampleFormat::F32 if cfg!(target_endian = "big") => ffi::SND_PCM_FORMAT_FLOAT_BE,
+            SampleFormat::F32 => ffi::SND_PCM_FORMAT_FLOAT_LE,
+        }
+    }
+
+    /// The inverse of [`Self::to_alsa`], for matching a [`SupportedFormat`] probed from a device
+    /// against the `SampleFormat`s this crate can actually transfer. `None` if ALSA's format
+    /// doesn't have an equivalent variant here (e.g. the signed 32-bit formats).
+    fn from_alsa(format: ffi::snd_pcm_format_t) -> Option<SampleFormat> {
+        match format {
+            ffi::SND_PCM_FORMAT_U8 => Some(SampleFormat::U8),
+            ffi::SND_PCM_FORMAT_S16_LE | ffi::SND_PCM_FORMAT_S16_BE => Some(SampleFormat::I16),
+            ffi::SND_PCM_FORMAT_FLOAT_LE | ffi::SND_PCM_FORMAT_FLOAT_BE => Some(SampleFormat::F32),
+            _ => None,
+        }
+    }
+}
+
 pub struct DeviceConfig {
     /// The target amount of time to store buffered audio for. The sound driver will use something
     /// close to this number, but it might not be exact.
     pub buffer_target_us: u32,
     /// The number of channels for playback. Channel data is always interleaved.
     pub channels: u32,
+    /// Whether this device plays audio out or records it.
+    pub direction: Direction,
     /// The target amount of time to process before asking the application for more data. The sound
     /// driver will use something close to this number, but it might not be exact.
     pub period_target_us: u32,
     /// The constant sample rate in hz to output audio at
     pub sample_rate: u32,
+    /// The sample representation to negotiate with the device.
+    pub sample_format: SampleFormat,
+    /// Called on the run loop's thread whenever an underrun or suspend is recovered from. Useful
+    /// for logging or metrics; the stream keeps playing regardless.
+    pub on_underrun: Option<Arc<dyn Fn(Error) + Send + Sync>>,
 }
 
+/// A view into [`Device`]'s internal buffer, typed to match the [`SampleFormat`] the device was
+/// opened with. `data_callback`s match on this rather than assuming `f32`.
 #[derive(Debug)]
+pub enum OutputBuffer<'a> {
+    U8(&'a mut VecDeque<u8>),
+    I16(&'a mut VecDeque<i16>),
+    F32(&'a mut VecDeque<f32>),
+}
+
+impl<'a> OutputBuffer<'a> {
+    /// Push an `f32` sample, converting it via `dasp::sample::conv` to whichever format the
+    /// device actually negotiated. Lets a caller whose signal chain produces `f32` (the common
+    /// case for `dasp` signals) target a device opened with any [`SampleFormat`].
+    pub fn push_f32(&mut self, sample: f32) {
+        match self {
+            OutputBuffer::U8(buf) => buf.push_back(conv::f32::to_u8(sample)),
+            OutputBuffer::I16(buf) => buf.push_back(conv::f32::to_i16(sample)),
+            OutputBuffer::F32(buf) => buf.push_back(sample),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum SampleBuffer {
+    U8(VecDeque<u8>),
+    I16(VecDeque<i16>),
+    F32(VecDeque<f32>),
+}
+
+impl SampleBuffer {
+    fn new(format: SampleFormat, capacity: usize) -> Self {
+        match format {
+            SampleFormat::U8 => SampleBuffer::U8(VecDeque::with_capacity(capacity)),
+            SampleFormat::I16 => SampleBuffer::I16(VecDeque::with_capacity(capacity)),
+            SampleFormat::F32 => SampleBuffer::F32(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        match self {
+            SampleBuffer::U8(buf) => buf.capacity(),
+            SampleBuffer::I16(buf) => buf.capacity(),
+            SampleBuffer::F32(buf) => buf.capacity(),
+        }
+    }
+
+    fn as_output_buffer(&mut self) -> OutputBuffer {
+        match self {
+            SampleBuffer::U8(buf) => OutputBuffer::U8(buf),
+            SampleBuffer::I16(buf) => OutputBuffer::I16(buf),
+            SampleBuffer::F32(buf) => OutputBuffer::F32(buf),
+        }
+    }
+
+    /// Write whatever's contiguous at the front of the buffer to the device, returning whatever
+    /// `snd_pcm_writei` returns (frames written, or a negative errno). `snd_pcm_writei` counts in
+    /// frames (one sample per channel), so the contiguous slice's element count has to be divided
+    /// by `channels` before it's passed in.
+    unsafe fn write_to(&self, handle: *mut ffi::snd_pcm_t, channels: u32) -> i64 {
+        match self {
+            SampleBuffer::U8(buf) => {
+                let (front, _) = buf.as_slices();
+                ffi::snd_pcm_writei(handle, front.as_ptr() as _, front.len() as u64 / channels as u64)
+            }
+            SampleBuffer::I16(buf) => {
+                let (front, _) = buf.as_slices();
+                ffi::snd_pcm_writei(handle, front.as_ptr() as _, front.len() as u64 / channels as u64)
+            }
+            SampleBuffer::F32(buf) => {
+                let (front, _) = buf.as_slices();
+                ffi::snd_pcm_writei(handle, front.as_ptr() as _, front.len() as u64 / channels as u64)
+            }
+        }
+    }
+
+    fn drain_front(&mut self, count: usize) {
+        match self {
+            SampleBuffer::U8(buf) => buf.drain(..count).for_each(drop),
+            SampleBuffer::I16(buf) => buf.drain(..count).for_each(drop),
+            SampleBuffer::F32(buf) => buf.drain(..count).for_each(drop),
+        }
+    }
+}
+
 pub struct Device {
     buffer_size: u64,
+    channels: u32,
     handle: *mut ffi::snd_pcm_t,
     period_size: u64,
     sample_rate: u32,
-    user_buffer: VecDeque<f32>,
+    user_buffer: SampleBuffer,
+    on_underrun: Option<Arc<dyn Fn(Error) + Send + Sync>>,
+}
+
+/// `run` moves the `Device` onto its own thread so `data_callback` never races the caller.
+unsafe impl Send for Device {}
+
+impl std::fmt::Debug for Device {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Device")
+            .field("buffer_size", &self.buffer_size)
+            .field("channels", &self.channels)
+            .field("handle", &self.handle)
+            .field("period_size", &self.period_size)
+            .field("sample_rate", &self.sample_rate)
+            .field("user_buffer", &self.user_buffer)
+            .finish()
+    }
 }
 
 impl Device {
-    pub fn with_config(config: &DeviceConfig) -> Result<Self, Error> {
+    /// Open `device` (a name as returned by [`devices`], e.g. `"default"` or `"hw:0,0"`) with the
+    /// given configuration.
+    pub fn with_config(device: &str, config: &DeviceConfig) -> Result<Self, Error> {
+        let device_name = CString::new(device).expect("device name contains a nul byte");
+        let stream = match config.direction {
+            Direction::Playback => ffi::SND_PCM_STREAM_PLAYBACK,
+            Direction::Capture => ffi::SND_PCM_STREAM_CAPTURE,
+        };
+
         let handle = unsafe {
-            let device_name = CStr::from_bytes_with_nul_unchecked(b"default\0").as_ptr();
             ptr_init!(*mut ffi::snd_pcm_t, |p| ffi::snd_pcm_open(
                     p,
-                    device_name,
-                    ffi::SND_PCM_STREAM_PLAYBACK,
+                    device_name.as_ptr(),
+                    stream,
                     ffi::SND_PCM_NONBLOCK
             ))?
         };
 
-        let fmt = if cfg!(target_endian = "big") {
-            ffi::SND_PCM_FORMAT_FLOAT_BE
-        } else {
-            ffi::SND_PCM_FORMAT_FLOAT_LE
-        };
+        let fmt = config.sample_format.to_alsa();
 
         let mut hw_params = HwParams::new()?;
         let mut sample_rate = config.sample_rate;
@@ -89,42 +648,158 @@ impl Device {
             code!(ffi::snd_pcm_prepare(handle))?;
         }
 
-        let user_buffer = VecDeque::with_capacity(buffer_size as usize);
+        let user_buffer = SampleBuffer::new(config.sample_format, buffer_size as usize);
+        let on_underrun = config.on_underrun.clone();
+
+        let channels = config.channels;
 
-        Ok(Self { buffer_size, handle, period_size, sample_rate, user_buffer })
+        Ok(Self { buffer_size, channels, handle, period_size, sample_rate, user_buffer, on_underrun })
     }
 
-    pub fn run<F>(mut self, mut data_callback: F)
-    where F: FnMut(&mut VecDeque<f32>, usize) {
+    /// Spawn the write loop on a background thread and return a [`StreamHandle`] to control it.
+    /// `data_callback` is driven from that thread, not the caller's.
+    pub fn run<F>(mut self, mut data_callback: F) -> Result<StreamHandle, Error>
+    where F: FnMut(OutputBuffer, usize) + Send + 'static {
+        let trigger = Arc::new(Trigger::new()?);
+        let running = Arc::new(AtomicBool::new(true));
+        let paused = Arc::new(AtomicBool::new(false));
+
+        let thread_trigger = trigger.clone();
+        let thread_running = running.clone();
+        let thread_paused = paused.clone();
+
+        thread::spawn(move || {
+            // Fill the buffer first
+            let wanted = self.user_buffer.capacity();
+            data_callback(self.user_buffer.as_output_buffer(), wanted);
+
+            let mut is_paused = false;
+
+            'outer: while thread_running.load(Ordering::SeqCst) {
+                let want_paused = thread_paused.load(Ordering::SeqCst);
+
+                if want_paused != is_paused {
+                    match unsafe { apply_pause_state(self.handle, want_paused) } {
+                        Ok(()) => is_paused = want_paused,
+                        Err(err) => panic!("Error applying pause state to sound device: {:?}", err),
+                    }
+                }
 
-        // Fill the buffer first
-        let wanted = self.user_buffer.capacity();
-        data_callback(&mut self.user_buffer, wanted);
+                if is_paused {
+                    match wait_for_trigger(thread_trigger.read_fd) {
+                        Ok(()) => thread_trigger.drain(),
+                        Err(_) => break 'outer,
+                    }
+                    continue 'outer;
+                }
 
-        loop {
-            unsafe {
-                let (buf, _) = self.user_buffer.as_slices();
+                unsafe {
+                    let ret = self.user_buffer.write_to(self.handle, self.channels);
+                    let errno = Errno::from_i32(-ret as i32);
+
+                    if Errno::EAGAIN == errno {
+                        match wait_for_activity(self.handle, thread_trigger.read_fd) {
+                            Ok(woken_by_trigger) => {
+                                if woken_by_trigger {
+                                    thread_trigger.drain();
+                                }
+                            }
+                            Err(_) => break 'outer,
+                        }
+                        continue 'outer;
+                    }
+
+                    if ret < 0 {
+                        match recover(self.handle, ret, &self.on_underrun) {
+                            Ok(()) => continue 'outer,
+                            Err(err) => panic!("Error writing to sound device: {:?}", err),
+                        }
+                    }
+
+                    // `ret` is frames written; convert back to an element count (frames *
+                    // channels) before draining the interleaved buffer or reporting to the
+                    // caller, both of which count in elements.
+                    let written = ret as usize * self.channels as usize;
+
+                    self.user_buffer.drain_front(written);
+
+                    data_callback(self.user_buffer.as_output_buffer(), written);
+                }
+            }
 
-                let ret = ffi::snd_pcm_writei(self.handle, buf.as_ptr() as _, buf.len() as u64);
-                let errno = Errno::from_i32(-ret as i32);
+            thread_running.store(false, Ordering::SeqCst);
+        });
 
-                if Errno::EAGAIN == errno {
-                    let ret = ffi::snd_pcm_wait(self.handle, -1);
-                    if ret < 0 { panic!("Failed to poll device") }
-                    continue;
-                }
+        Ok(StreamHandle { trigger, running, paused })
+    }
 
-                if ret < 0 {
-                    panic!("Error writing to sound device");
+    /// Spawn the capture loop on a background thread and return a [`StreamHandle`] to control
+    /// it. Requires the device to have been opened with [`Direction::Capture`].
+    pub fn run_capture<F>(self, mut data_callback: F) -> Result<StreamHandle, Error>
+    where F: FnMut(&[f32]) + Send + 'static {
+        let trigger = Arc::new(Trigger::new()?);
+        let running = Arc::new(AtomicBool::new(true));
+        let paused = Arc::new(AtomicBool::new(false));
+
+        let thread_trigger = trigger.clone();
+        let thread_running = running.clone();
+        let thread_paused = paused.clone();
+
+        thread::spawn(move || {
+            // snd_pcm_readi fills interleaved channels per frame, so the scratch buffer needs
+            // room for `channels` samples per frame, not just one.
+            let mut scratch = vec![0f32; self.period_size as usize * self.channels as usize];
+            let mut is_paused = false;
+
+            'outer: while thread_running.load(Ordering::SeqCst) {
+                let want_paused = thread_paused.load(Ordering::SeqCst);
+
+                if want_paused != is_paused {
+                    match unsafe { apply_pause_state(self.handle, want_paused) } {
+                        Ok(()) => is_paused = want_paused,
+                        Err(err) => panic!("Error applying pause state to sound device: {:?}", err),
+                    }
                 }
 
-                for _ in 0..ret {
-                    self.user_buffer.pop_front();
+                if is_paused {
+                    match wait_for_trigger(thread_trigger.read_fd) {
+                        Ok(()) => thread_trigger.drain(),
+                        Err(_) => break 'outer,
+                    }
+                    continue 'outer;
                 }
 
-                data_callback(&mut self.user_buffer, ret as usize);
+                unsafe {
+                    let ret = ffi::snd_pcm_readi(self.handle, scratch.as_mut_ptr() as _, self.period_size);
+                    let errno = Errno::from_i32(-ret as i32);
+
+                    if Errno::EAGAIN == errno {
+                        match wait_for_activity(self.handle, thread_trigger.read_fd) {
+                            Ok(woken_by_trigger) => {
+                                if woken_by_trigger {
+                                    thread_trigger.drain();
+                                }
+                            }
+                            Err(_) => break 'outer,
+                        }
+                        continue 'outer;
+                    }
+
+                    if ret < 0 {
+                        match recover(self.handle, ret, &self.on_underrun) {
+                            Ok(()) => continue 'outer,
+                            Err(err) => panic!("Error reading from sound device: {:?}", err),
+                        }
+                    }
+
+                    data_callback(&scratch[..ret as usize * self.channels as usize]);
+                }
             }
-        }
+
+            thread_running.store(false, Ordering::SeqCst);
+        });
+
+        Ok(StreamHandle { trigger, running, paused })
     }
 }
 
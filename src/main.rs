@@ -1,13 +1,13 @@
 #[macro_use]
 mod macros;
 mod alsa;
+mod mixer;
 
-use alsa::{Device, DeviceConfig};
+use alsa::{Device, DeviceConfig, Direction, SampleFormat};
 use dasp::signal::{self as signal, Signal};
 use dasp::sample::conv;
 use std::collections::VecDeque;
 use std::sync::{Arc, Mutex, mpsc};
-use std::thread;
 
 const SAMPLE_RATE: u32 = 44_100;
 
@@ -28,30 +28,33 @@ fn main() {
     let buffer = Arc::new(Mutex::new(buffer));
     let t_buffer = buffer.clone();
 
-    thread::spawn(|| {
-        let config = DeviceConfig {
-            sample_rate: SAMPLE_RATE,
-            channels: 1,
-            buffer_target_us: 42_000,
-            period_target_us: 8_000,
-        };
+    let config = DeviceConfig {
+        sample_rate: SAMPLE_RATE,
+        channels: 1,
+        direction: Direction::Playback,
+        buffer_target_us: 42_000,
+        period_target_us: 8_000,
+        sample_format: SampleFormat::F32,
+        on_underrun: None,
+    };
 
-        let device = Device::with_config(config).unwrap();
-        println!("{:#?}", device);
+    let device = Device::with_config("default", &config).unwrap();
+    println!("{:#?}", device);
 
-        device.run(move |queue, wanted| {
-            let mut buffer = t_buffer.lock().unwrap();
+    // `run` drives the device from its own thread; the returned handle is what pauses, resumes
+    // or stops it. Dropping the handle does not stop the stream.
+    let _stream = device.run(move |mut output, wanted| {
+        let mut buffer = t_buffer.lock().unwrap();
 
-            for _ in 0..wanted {
-                match buffer.pop_front() {
-                    Some(sample) => queue.push_back(sample),
-                    None => println!("Not enough data!!"),
-                };
-            }
+        for _ in 0..wanted {
+            match buffer.pop_front() {
+                Some(sample) => output.push_f32(sample),
+                None => println!("Not enough data!!"),
+            };
+        }
 
-            tx.send(Message::WantMoreData).unwrap();
-        });
-    });
+        tx.send(Message::WantMoreData).unwrap();
+    }).unwrap();
 
     loop {
         let msg = rx.recv().unwrap();
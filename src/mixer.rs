@@ -0,0 +1,188 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// A block of samples from one source, tagged with the device-clock frame it becomes due at.
+/// Frames should be pushed to an [`AudioSource`] in non-decreasing `clock` order.
+#[derive(Debug, Clone)]
+pub struct AudioFrame {
+    pub clock: u64,
+    pub data: Vec<f32>,
+}
+
+struct Source {
+    native_rate: u32,
+    /// Frames that aren't due yet, ordered by `clock`.
+    pending: VecDeque<AudioFrame>,
+    /// Flattened samples from frames that have come due, at the source's native rate.
+    ready: VecDeque<f32>,
+    /// Fractional read position into `ready`, used to linearly interpolate between samples when
+    /// `native_rate` doesn't match the mixer's output rate.
+    cursor: f64,
+}
+
+impl Source {
+    fn pull(&mut self, device_clock: u64, device_rate: u32, out: &mut [f32]) {
+        while let Some(front) = self.pending.front() {
+            if front.clock > device_clock {
+                break;
+            }
+
+            let frame = self.pending.pop_front().unwrap();
+            self.ready.extend(frame.data);
+        }
+
+        let ratio = self.native_rate as f64 / device_rate as f64;
+
+        for slot in out.iter_mut() {
+            let base = self.cursor.floor() as usize;
+            let frac = self.cursor - base as f64;
+
+            let s0 = self.ready.get(base).copied().unwrap_or(0.0);
+            let s1 = self.ready.get(base + 1).copied().unwrap_or(s0);
+
+            *slot += s0 + (s1 - s0) * frac as f32;
+            self.cursor += ratio;
+        }
+
+        // A shortfall (not enough `ready` samples to cover `out`) still advances `cursor` once
+        // per slot above, since the interpolation loop doesn't know it's reading past the end.
+        // Clamp it back to what's actually in `ready` so the backlog doesn't persist: otherwise
+        // the next frames pushed in would be considered "already consumed" and get silently
+        // dropped instead of mixed in.
+        let available = self.ready.len() as f64;
+        if self.cursor > available {
+            self.cursor = available;
+        }
+
+        let consumed = self.cursor.floor() as usize;
+        self.ready.drain(..consumed);
+        self.cursor -= consumed as f64;
+    }
+}
+
+/// A feed into an [`AudioMixer`]. Cloning an `AudioSource` is not supported; instead call
+/// [`AudioMixer::add_source`] once per independent stream and hand the returned handle to
+/// whatever produces its audio (a tone generator, a sample player, a capture callback, ...).
+pub struct AudioSource {
+    source: Arc<Mutex<Source>>,
+}
+
+impl AudioSource {
+    /// Queue a frame of audio to be mixed in once the mixer's clock reaches `frame.clock`.
+    pub fn push_frame(&self, frame: AudioFrame) {
+        self.source.lock().unwrap().pending.push_back(frame);
+    }
+}
+
+/// Sums several independently-clocked [`AudioSource`]s into one output stream at a fixed sample
+/// rate, resampling each source to match as it mixes.
+pub struct AudioMixer {
+    sample_rate: u32,
+    frame_size: usize,
+    clock: Mutex<u64>,
+    sources: Mutex<Vec<Arc<Mutex<Source>>>>,
+}
+
+impl AudioMixer {
+    pub fn new(sample_rate: u32, frame_size: usize) -> Self {
+        Self {
+            sample_rate,
+            frame_size,
+            clock: Mutex::new(0),
+            sources: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    /// Register a new source running at `native_rate`, returning a handle to feed it frames.
+    pub fn add_source(&self, native_rate: u32) -> AudioSource {
+        let source = Arc::new(Mutex::new(Source {
+            native_rate,
+            pending: VecDeque::new(),
+            ready: VecDeque::new(),
+            cursor: 0.0,
+        }));
+
+        self.sources.lock().unwrap().push(source.clone());
+
+        AudioSource { source }
+    }
+
+    /// Mix `wanted` frames from every registered source, clamped to `[-1.0, 1.0]`. Sources with
+    /// nothing due yet contribute silence instead of stalling the mix.
+    pub fn mix(&self, wanted: usize) -> VecDeque<f32> {
+        let mut out = vec![0.0f32; wanted];
+        let mut clock = self.clock.lock().unwrap();
+
+        for source in self.sources.lock().unwrap().iter() {
+            source.lock().unwrap().pull(*clock, self.sample_rate, &mut out);
+        }
+
+        for sample in out.iter_mut() {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+
+        *clock += wanted as u64;
+
+        out.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_source(native_rate: u32) -> Source {
+        Source { native_rate, pending: VecDeque::new(), ready: VecDeque::new(), cursor: 0.0 }
+    }
+
+    #[test]
+    fn pull_mixes_a_due_frame_at_matching_rates() {
+        let mut source = new_source(44_100);
+        source.pending.push_back(AudioFrame { clock: 0, data: vec![1.0, 0.5] });
+
+        let mut out = vec![0.0; 2];
+        source.pull(0, 44_100, &mut out);
+
+        assert_eq!(out, vec![1.0, 0.5]);
+    }
+
+    #[test]
+    fn pull_leaves_a_not_yet_due_frame_pending() {
+        let mut source = new_source(44_100);
+        source.pending.push_back(AudioFrame { clock: 10, data: vec![1.0] });
+
+        let mut out = vec![0.0; 1];
+        source.pull(0, 44_100, &mut out);
+
+        assert_eq!(out, vec![0.0]);
+        assert_eq!(source.pending.len(), 1);
+    }
+
+    #[test]
+    fn pull_clamps_cursor_after_a_shortfall_so_later_frames_still_mix() {
+        let mut source = new_source(44_100);
+        source.pending.push_back(AudioFrame { clock: 0, data: vec![1.0] });
+
+        // Ask for more than the one sample that's actually ready. Without clamping `cursor`
+        // back down to what's in `ready`, it would end up past `ready.len()` and the frame
+        // pushed below would look "already consumed" and get silently dropped instead of
+        // getting mixed in.
+        let mut out = vec![0.0; 4];
+        source.pull(0, 44_100, &mut out);
+        assert_eq!(out, vec![1.0, 0.0, 0.0, 0.0]);
+
+        source.pending.push_back(AudioFrame { clock: 0, data: vec![0.5] });
+        let mut out = vec![0.0; 1];
+        source.pull(0, 44_100, &mut out);
+
+        assert_eq!(out, vec![0.5]);
+    }
+}